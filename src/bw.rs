@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use config::Config;
+use url::Url;
+
+use crate::Error;
+
+const DEFAULT_BASE_URL: &str = "https://vault.bitwarden.com";
+const DEFAULT_IDENTITY_URL: &str = "https://identity.bitwarden.com";
+
+/// Thin wrapper around the `bw` CLI, caching the unlocked session token and
+/// whether the server has been configured between calls.
+#[derive(Clone)]
+pub struct BitwardenClientWrapper {
+    session_token: Option<String>,
+    server_configured: bool,
+    client_id: String,
+    client_secret: String,
+    base_url: Url,
+    identity_url: Url,
+    api_url: Option<Url>,
+    web_vault_url: Option<Url>,
+}
+
+impl BitwardenClientWrapper {
+    pub fn new(config: Config) -> Result<Self, Error> {
+        Ok(BitwardenClientWrapper {
+            session_token: None,
+            server_configured: false,
+            client_id: config.get_string("bw_client_id").unwrap_or_default(),
+            client_secret: config.get_string("bw_client_secret").unwrap_or_default(),
+            base_url: parse_url(&config, "base_url", DEFAULT_BASE_URL)?,
+            identity_url: parse_url(&config, "identity_url", DEFAULT_IDENTITY_URL)?,
+            api_url: parse_optional_url(&config, "api_url")?,
+            web_vault_url: parse_optional_url(&config, "web_vault_url")?,
+        })
+    }
+
+    /// Fetches a single vault item's fields, keyed by field name.
+    pub fn fetch_item(&mut self, path: String) -> Result<BTreeMap<String, String>, Error> {
+        let session = self.session()?;
+
+        let output = Command::new("bw")
+            .args(["get", "item", &path, "--session", &session])
+            .output()
+            .map_err(|e| Error::UserInputError(format!("Failed to invoke bw CLI: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(Error::UserInputError(format!(
+                "bw get item {} failed: {}",
+                path,
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        parse_item_fields(&output.stdout)
+    }
+
+    /// Drops the cached session, forcing the next `fetch_item` to unlock again.
+    pub fn reset(&mut self) {
+        self.session_token = None;
+    }
+
+    /// Points the `bw` CLI at the configured server; only needs to run once per process.
+    fn configure_server(&mut self) -> Result<(), Error> {
+        if self.server_configured {
+            return Ok(());
+        }
+
+        let mut args = vec!["config".to_string(), "server".to_string(), self.base_url.to_string()];
+        args.push("--identity".to_string());
+        args.push(self.identity_url.to_string());
+        if let Some(api_url) = &self.api_url {
+            args.push("--api".to_string());
+            args.push(api_url.to_string());
+        }
+        if let Some(web_vault_url) = &self.web_vault_url {
+            args.push("--web-vault".to_string());
+            args.push(web_vault_url.to_string());
+        }
+
+        let output = Command::new("bw")
+            .args(&args)
+            .output()
+            .map_err(|e| Error::UserInputError(format!("Failed to invoke bw CLI: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(Error::UserInputError(format!(
+                "bw config server failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        self.server_configured = true;
+        Ok(())
+    }
+
+    fn session(&mut self) -> Result<String, Error> {
+        if let Some(token) = &self.session_token {
+            return Ok(token.clone());
+        }
+
+        self.configure_server()?;
+
+        let output = Command::new("bw")
+            .args(["unlock", "--apikey"])
+            .env("BW_CLIENTID", &self.client_id)
+            .env("BW_CLIENTSECRET", &self.client_secret)
+            .output()
+            .map_err(|e| Error::UserInputError(format!("Failed to invoke bw CLI: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(Error::UserInputError(format!(
+                "bw unlock failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        self.session_token = Some(token.clone());
+        Ok(token)
+    }
+}
+
+/// Parses a required URL config key, falling back to `default` when unset.
+fn parse_url(config: &Config, key: &str, default: &str) -> Result<Url, Error> {
+    let raw = config.get_string(key).unwrap_or_else(|_| default.to_string());
+    Url::parse(&raw).map_err(|e| Error::UserInputError(format!("Invalid `{}`: {}", key, e)))
+}
+
+/// Parses an optional URL config key, returning `None` when unset.
+fn parse_optional_url(config: &Config, key: &str) -> Result<Option<Url>, Error> {
+    match config.get_string(key) {
+        Ok(raw) => Url::parse(&raw)
+            .map(Some)
+            .map_err(|e| Error::UserInputError(format!("Invalid `{}`: {}", key, e))),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with(key: &str, value: &str) -> Config {
+        Config::builder().set_override(key, value).unwrap().build().unwrap()
+    }
+
+    #[test]
+    fn parse_url_falls_back_to_default_when_unset() {
+        let config = Config::builder().build().unwrap();
+        let url = parse_url(&config, "base_url", DEFAULT_BASE_URL).unwrap();
+        assert_eq!(url.as_str(), "https://vault.bitwarden.com/");
+    }
+
+    #[test]
+    fn parse_url_rejects_malformed_url() {
+        let config = config_with("base_url", "not a url");
+        assert!(parse_url(&config, "base_url", DEFAULT_BASE_URL).is_err());
+    }
+
+    #[test]
+    fn parse_optional_url_is_none_when_unset() {
+        let config = Config::builder().build().unwrap();
+        assert_eq!(parse_optional_url(&config, "api_url").unwrap(), None);
+    }
+
+    #[test]
+    fn parse_optional_url_rejects_malformed_url() {
+        let config = config_with("api_url", "not a url");
+        assert!(parse_optional_url(&config, "api_url").is_err());
+    }
+}
+
+fn parse_item_fields(raw: &[u8]) -> Result<BTreeMap<String, String>, Error> {
+    let item: serde_json::Value = serde_json::from_slice(raw)
+        .map_err(|e| Error::UserInputError(format!("Invalid bw item JSON: {}", e)))?;
+
+    let mut fields = BTreeMap::new();
+    if let Some(login) = item.get("login") {
+        if let Some(username) = login.get("username").and_then(|v| v.as_str()) {
+            fields.insert("username".to_string(), username.to_string());
+        }
+        if let Some(password) = login.get("password").and_then(|v| v.as_str()) {
+            fields.insert("password".to_string(), password.to_string());
+        }
+    }
+    if let Some(custom_fields) = item.get("fields").and_then(|v| v.as_array()) {
+        for field in custom_fields {
+            if let (Some(name), Some(value)) = (
+                field.get("name").and_then(|v| v.as_str()),
+                field.get("value").and_then(|v| v.as_str()),
+            ) {
+                fields.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+
+    Ok(fields)
+}
@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A single Bitwarden vault item to fetch, addressed in `templates` via `alias`.
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct BitwardenInput {
+    pub alias: String,
+    pub item: String,
+}
+
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[kube(
+    group = "tomjo.net",
+    version = "v1",
+    kind = "BitwardenSecret",
+    plural = "bitwardensecrets",
+    singular = "bitwardensecret",
+    shortname = "bws",
+    namespaced,
+    status = "BitwardenSecretStatus"
+)]
+pub struct BitwardenSecretSpec {
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Single vault item path, e.g. `"homelab/argo-minio"`. Ignored when `inputs` or `templates` is set.
+    #[serde(default)]
+    pub item: Option<String>,
+    /// Renames fetched field names to secret key names when using `item`, dropping fields it doesn't
+    /// name; passes all fields through unchanged when unset.
+    #[serde(default, rename = "keyMap")]
+    pub key_map: Option<BTreeMap<String, String>>,
+    /// Vault items this secret is composed from, keyed by their `templates` alias.
+    #[serde(default)]
+    pub inputs: Vec<BitwardenInput>,
+    /// Output secret key name -> Handlebars template, rendered against the fetched inputs.
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
+}
+
+/// Reports why a `BitwardenSecret` does or doesn't currently have a Secret.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+pub struct BitwardenSecretStatus {
+    #[serde(default)]
+    pub phase: String,
+    #[serde(default)]
+    pub conditions: Vec<BitwardenSecretCondition>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct BitwardenSecretCondition {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub status: String,
+    pub reason: String,
+    pub message: String,
+    #[serde(rename = "lastTransitionTime")]
+    pub last_transition_time: String,
+}
@@ -5,12 +5,15 @@ extern crate log;
 
 use std::{env};
 use std::borrow::{Cow, ToOwned};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use std::sync::Arc;
 
+use chrono::Utc;
 use futures::stream::StreamExt;
+use handlebars::Handlebars;
 use k8s_openapi::api::core::v1::{Secret};
+use k8s_openapi::ByteString;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
 use kube::Resource;
 use kube::ResourceExt;
@@ -22,20 +25,24 @@ use kube::api::{DeleteParams, Patch, PatchParams, PostParams};
 use serde_json::{json, Value};
 use config::Config;
 use const_format::formatcp;
-use crate::bw::BitwardenClientWrapper;
+use tokio::sync::Mutex;
 
-use crate::crd::BitwardenSecret;
+use crate::crd::{BitwardenInput, BitwardenSecret, BitwardenSecretSpec};
+use crate::provider::SecretProvider;
 
 pub mod crd;
 mod bw;
+mod provider;
 // mod bitwarden;
 
 const BW_OPERATOR_ENV_PREFIX: &'static str = "BW_OPERATOR";
 const ENV_CONFIG_PATH: &'static str = formatcp!("{}_CONFIG", BW_OPERATOR_ENV_PREFIX);
 const DEFAULT_CONFIG_PATH: &'static str = "config/config";
 
-// TODO add status
-// TODO Watch secret deletion, if owner refs contains a bitwardensecret, recreate
+const MIN_BACKOFF_SECS: u64 = 5;
+const MAX_BACKOFF_SECS: u64 = 300;
+const DEFAULT_RESYNC_INTERVAL_SECS: u64 = 300;
+
 #[tokio::main]
 async fn main() {
     env::set_var("RUST_LOG", "info");
@@ -47,14 +54,23 @@ async fn main() {
         .build()
         .expect("Could not initialize config");
 
-    let bw_client = BitwardenClientWrapper::new(config);
+    let resync_interval = Duration::from_secs(
+        config.get_int("resync_interval_secs").map(|secs| secs as u64).unwrap_or(DEFAULT_RESYNC_INTERVAL_SECS),
+    );
+
+    let bw_client = provider::build_provider(config).expect("Could not initialize secret provider");
 
     let kubernetes_client: Client = Client::try_default()
         .await
         .expect("Expected a valid KUBECONFIG environment variable.");
 
     let crd_api: Api<BitwardenSecret> = Api::all(kubernetes_client.clone());
-    let context: Arc<ContextData> = Arc::new(ContextData::new(kubernetes_client.clone(), bw_client));
+    let secret_api: Api<Secret> = Api::all(kubernetes_client.clone());
+    let context: Arc<ContextData> = Arc::new(ContextData::new(
+        kubernetes_client.clone(),
+        Arc::new(Mutex::new(bw_client)),
+        resync_interval,
+    ));
 
     // The controller comes from the `kube_runtime` crate and manages the reconciliation process.
     // It requires the following information:
@@ -62,7 +78,9 @@ async fn main() {
     // - `kube::api::ListParams` to select the `BitwardenSecret` resources with. Can be used for BitwardenSecret filtering `BitwardenSecret` resources before reconciliation,
     // - `reconcile` function with reconciliation logic to be called each time a resource of `BitwardenSecret` kind is created/updated/deleted,
     // - `on_error` function to call whenever reconciliation fails.
+    // `.owns(secret_api, ...)` also re-triggers `reconcile` on changes to owned `Secret`s.
     Controller::new(crd_api.clone(), ListParams::default())
+        .owns(secret_api, ListParams::default())
         .run(reconcile, on_error, context)
         .for_each(|reconciliation_result| async move {
             match reconciliation_result {
@@ -79,24 +97,30 @@ async fn main() {
 
 struct ContextData {
     client: Client,
-    bw_client: BitwardenClientWrapper,
+    bw_client: Arc<Mutex<Box<dyn SecretProvider>>>,
+    /// Consecutive reconcile failures per `"{namespace}/{name}"`, for backoff.
+    failure_counts: std::sync::Mutex<HashMap<String, u32>>,
+    /// How often a reconciled `BitwardenSecret` is re-checked against the vault.
+    resync_interval: Duration,
 }
 
 impl ContextData {
-    pub fn new(client: Client, bw_client: BitwardenClientWrapper) -> Self {
-        ContextData { client, bw_client }
+    pub fn new(client: Client, bw_client: Arc<Mutex<Box<dyn SecretProvider>>>, resync_interval: Duration) -> Self {
+        ContextData { client, bw_client, failure_counts: std::sync::Mutex::new(HashMap::new()), resync_interval }
     }
 }
 
 enum BitwardenSecretAction {
     Create,
+    /// Finalized, but the owned `Secret` is missing and must be recreated.
+    Recreate,
     Delete,
-    NoOp,
+    /// Carries the already-fetched `Secret` to avoid a second `get_opt`.
+    NoOp(Box<Secret>),
 }
 
 async fn reconcile(bitwarden_secret: Arc<BitwardenSecret>, context: Arc<ContextData>) -> Result<Action, Error> {
     let client: Client = context.client.clone(); // The `Client` is shared -> a clone from the reference is obtained
-    let mut bw_client: BitwardenClientWrapper = context.bw_client.clone(); // The `Client` is shared -> a clone from the reference is obtained
 
     // The resource of `BitwardenSecret` kind is required to have a namespace set. However, it is not guaranteed
     // the resource will have a `namespace` set. Therefore, the `namespace` field on object's metadata
@@ -107,49 +131,147 @@ async fn reconcile(bitwarden_secret: Arc<BitwardenSecret>, context: Arc<ContextD
     };
 
     let name = bitwarden_secret.name_any();
+    let failure_key = format!("{}/{}", namespace, name);
 
-    return match determine_action(&bitwarden_secret) {
+    return match determine_action(&bitwarden_secret, client.clone()).await? {
         BitwardenSecretAction::Create => {
             add_finalizer(client.clone(), &name, &namespace).await?;
-
-            let mut labels: BTreeMap<String, String> = BTreeMap::new();
-            labels.insert("app".to_owned(), name.to_owned());
-            // TODO copy labels (all but?)
-
-            let result = bw_client.fetch_item("homelab/argo-minio".to_string());
-            if result.is_err() {
-                info!("Resetting bw context");
-                if let Some(e) = result.err() {
-                    info!("source: {}", e.to_string())
-                }
-                bw_client.reset();
-            } else {
-                let secret_keys: BTreeMap<String, String> = result.unwrap();
-
-
-                let owner_ref = OwnerReference {
-                    // api_version: api_v_test(bitwarden_secret.as_ref()),
-                    // kind: kind_test(bitwarden_secret.as_ref()),
-                    api_version: "tomjo.net/v1".to_string(),
-                    kind: "BitwardenSecret".to_string(),
-                    name: name.clone(),
-                    uid: bitwarden_secret.uid().expect(&format!("Bitwarden secret without uid: {}/{}", namespace, &name)),
-                    block_owner_deletion: Some(true),
-                    controller: None,
-                };
-
-
-                create_secret(client, owner_ref, &name, &namespace, &bitwarden_secret.spec.type_, secret_keys, labels).await?;
-            }
+            let secret_keys = fetch_secret_keys(&context, client.clone(), &bitwarden_secret, &name, &namespace).await?;
+            reconcile_secret(client, secret_keys, &bitwarden_secret, &name, &namespace).await?;
+            clear_failure_count(&context, &failure_key);
+            Ok(Action::requeue(Duration::from_secs(10)))
+        }
+        BitwardenSecretAction::Recreate => {
+            let secret_keys = fetch_secret_keys(&context, client.clone(), &bitwarden_secret, &name, &namespace).await?;
+            reconcile_secret(client, secret_keys, &bitwarden_secret, &name, &namespace).await?;
+            clear_failure_count(&context, &failure_key);
             Ok(Action::requeue(Duration::from_secs(10)))
         }
         BitwardenSecretAction::Delete => {
             delete_secret(client.clone(), &name, &namespace).await?;
             delete_finalizer(client, &name, &namespace).await?;
+            clear_failure_count(&context, &failure_key);
             Ok(Action::await_change())
         }
-        BitwardenSecretAction::NoOp => Ok(Action::requeue(Duration::from_secs(10))),
+        BitwardenSecretAction::NoOp(existing) => {
+            let secret_keys = fetch_secret_keys(&context, client.clone(), &bitwarden_secret, &name, &namespace).await?;
+            resync_secret(client, secret_keys, &name, &namespace, *existing).await?;
+            clear_failure_count(&context, &failure_key);
+            Ok(Action::requeue(context.resync_interval))
+        }
+    };
+}
+
+/// Resolves the `BitwardenSecret`'s vault data, locking `bw_client` only for
+/// the fetch itself. Patches `status` and propagates the error on failure.
+async fn fetch_secret_keys(
+    context: &ContextData,
+    client: Client,
+    bitwarden_secret: &BitwardenSecret,
+    name: &str,
+    namespace: &str,
+) -> Result<BTreeMap<String, String>, Error> {
+    let secret_keys = {
+        let mut bw_client = context.bw_client.lock().await;
+        resolve_secret_keys(&mut **bw_client, &bitwarden_secret.spec)
     };
+
+    match secret_keys {
+        Ok(secret_keys) => Ok(secret_keys),
+        Err(e) => {
+            patch_status(client, name, namespace, "Failed", "False", "FetchFailed", &e.to_string()).await?;
+            Err(e)
+        }
+    }
+}
+
+/// Patches the `Secret` only when the vault data actually diverges from
+/// what's live, so a rotated value is picked up without needless churn.
+async fn resync_secret(
+    client: Client,
+    secret_keys: BTreeMap<String, String>,
+    name: &str,
+    namespace: &str,
+    existing: Secret,
+) -> Result<(), Error> {
+    let secret_api: Api<Secret> = Api::namespaced(client.clone(), namespace);
+    let changed = secret_data_differs(&existing, &secret_keys);
+
+    if changed {
+        let patch = data_patch(&existing, &secret_keys);
+        secret_api.patch(name, &PatchParams::default(), &Patch::Merge(&patch)).await?;
+        patch_status(client, name, namespace, "Ready", "True", "SecretResynced", "Bitwarden item(s) changed; Secret resynced").await?;
+    }
+    Ok(())
+}
+
+/// Compares a live `Secret`'s decoded `data` against the freshly-fetched vault fields.
+fn secret_data_differs(secret: &Secret, secret_keys: &BTreeMap<String, String>) -> bool {
+    &decode_secret_data(secret) != secret_keys
+}
+
+fn decode_secret_data(secret: &Secret) -> BTreeMap<String, String> {
+    secret
+        .data
+        .as_ref()
+        .map(|data| {
+            data.iter()
+                .filter_map(|(key, value)| String::from_utf8(value.0.clone()).ok().map(|v| (key.clone(), v)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Builds a `data` merge patch, nulling out keys in `existing` that are no
+/// longer in `secret_keys` since a merge patch can otherwise only add/overwrite.
+fn data_patch(existing: &Secret, secret_keys: &BTreeMap<String, String>) -> Value {
+    let mut data = serde_json::Map::new();
+    for (key, value) in secret_keys {
+        data.insert(key.clone(), json!(ByteString(value.clone().into_bytes())));
+    }
+    for key in decode_secret_data(existing).keys() {
+        if !secret_keys.contains_key(key) {
+            data.insert(key.clone(), Value::Null);
+        }
+    }
+    json!({ "data": data })
+}
+
+/// Creates the `Secret` from already-resolved vault data, used by both the
+/// `Create` and `Recreate` actions.
+async fn reconcile_secret(
+    client: Client,
+    secret_keys: BTreeMap<String, String>,
+    bitwarden_secret: &BitwardenSecret,
+    name: &str,
+    namespace: &str,
+) -> Result<(), Error> {
+    let mut labels: BTreeMap<String, String> = bitwarden_secret.labels().clone();
+    labels.insert("app".to_owned(), name.to_owned());
+    let annotations: BTreeMap<String, String> = bitwarden_secret.annotations().clone();
+
+    let owner_ref = build_owner_ref(bitwarden_secret, name, namespace);
+
+    create_secret(client.clone(), owner_ref, name, namespace, &bitwarden_secret.spec.type_, secret_keys, labels, annotations).await?;
+    patch_status(client, name, namespace, "Ready", "True", "SecretCreated", "Bitwarden item(s) fetched and Secret created").await?;
+    Ok(())
+}
+
+/// Builds the `Secret`'s owner reference. `controller: Some(true)` is load
+/// bearing: the `.owns()` watch only re-triggers for the controlling owner.
+fn build_owner_ref(bitwarden_secret: &BitwardenSecret, name: &str, namespace: &str) -> OwnerReference {
+    OwnerReference {
+        api_version: "tomjo.net/v1".to_string(),
+        kind: "BitwardenSecret".to_string(),
+        name: name.to_owned(),
+        uid: bitwarden_secret.uid().expect(&format!("Bitwarden secret without uid: {}/{}", namespace, name)),
+        block_owner_deletion: Some(true),
+        controller: Some(true),
+    }
+}
+
+fn clear_failure_count(context: &ContextData, key: &str) {
+    context.failure_counts.lock().expect("failure_counts lock poisoned").remove(key);
 }
 
 pub fn api_v_test<T: Resource<DynamicType=()>>(resource: &BitwardenSecret) -> String {
@@ -166,18 +288,29 @@ pub fn kind_test<T: Resource<DynamicType=()>>(resource: &BitwardenSecret) -> Str
     // .uid_opt(resource.meta().uid.clone());
 }
 
-fn determine_action(bitwarden_secret: &BitwardenSecret) -> BitwardenSecretAction {
-    return if bitwarden_secret.meta().deletion_timestamp.is_some() {
-        BitwardenSecretAction::Delete
-    } else if bitwarden_secret
+async fn determine_action(bitwarden_secret: &BitwardenSecret, client: Client) -> Result<BitwardenSecretAction, Error> {
+    if bitwarden_secret.meta().deletion_timestamp.is_some() {
+        return Ok(BitwardenSecretAction::Delete);
+    }
+
+    let has_finalizer = bitwarden_secret
         .meta()
         .finalizers
         .as_ref()
-        .map_or(true, |finalizers| finalizers.is_empty()) {
-        BitwardenSecretAction::Create
-    } else {
-        BitwardenSecretAction::NoOp
-    };
+        .map_or(false, |finalizers| !finalizers.is_empty());
+
+    if !has_finalizer {
+        return Ok(BitwardenSecretAction::Create);
+    }
+
+    let namespace = bitwarden_secret.namespace().unwrap_or_else(|| "default".to_string());
+    let secret_api: Api<Secret> = Api::namespaced(client, &namespace);
+    let existing = secret_api.get_opt(&bitwarden_secret.name_any()).await?;
+
+    Ok(match existing {
+        Some(secret) => BitwardenSecretAction::NoOp(Box::new(secret)),
+        None => BitwardenSecretAction::Recreate,
+    })
 }
 
 /// TODO Note: Does not check for resource's existence for simplicity.
@@ -218,12 +351,14 @@ pub async fn create_secret(
     type_: &str,
     secret_keys: BTreeMap<String, String>,
     labels: BTreeMap<String, String>,
+    annotations: BTreeMap<String, String>,
 ) -> Result<Secret, KubeError> {
     let secret: Secret = Secret {
         metadata: ObjectMeta {
             name: Some(name.to_owned()),
             namespace: Some(namespace.to_owned()),
             labels: Some(labels.clone()),
+            annotations: Some(annotations.clone()),
             owner_references: Some(vec![owner_ref]),
             ..ObjectMeta::default()
         },
@@ -247,9 +382,147 @@ pub async fn delete_secret(client: Client, name: &str, namespace: &str) -> Resul
     Ok(())
 }
 
-fn on_error(bitwarden_secret: Arc<BitwardenSecret>, error: &Error, _context: Arc<ContextData>) -> Action {
+/// Patches the `BitwardenSecret`'s status subresource with a single `Ready` condition.
+pub async fn patch_status(
+    client: Client,
+    name: &str,
+    namespace: &str,
+    phase: &str,
+    condition_status: &str,
+    reason: &str,
+    message: &str,
+) -> Result<(), Error> {
+    let api: Api<BitwardenSecret> = Api::namespaced(client, namespace);
+    let status: Value = json!({
+        "status": {
+            "phase": phase,
+            "conditions": [{
+                "type": "Ready",
+                "status": condition_status,
+                "reason": reason,
+                "message": message,
+                "lastTransitionTime": Utc::now().to_rfc3339(),
+            }]
+        }
+    });
+
+    let patch: Patch<&Value> = Patch::Merge(&status);
+    api.patch_status(name, &PatchParams::default(), &patch).await?;
+    Ok(())
+}
+
+/// Resolves a `BitwardenSecret`'s `spec` into its `Secret` data. Prefers the
+/// templating path when `inputs`/`templates` is set, else the single-`item` path.
+fn resolve_secret_keys(
+    bw_client: &mut dyn SecretProvider,
+    spec: &BitwardenSecretSpec,
+) -> Result<BTreeMap<String, String>, Error> {
+    if !spec.inputs.is_empty() || !spec.templates.is_empty() {
+        return match fetch_template_context(bw_client, &spec.inputs) {
+            Ok(render_ctx) => render_templates(&spec.templates, &render_ctx),
+            Err(e) => {
+                info!("Resetting bw context");
+                info!("source: {}", e.to_string());
+                bw_client.reset();
+                Err(e)
+            }
+        };
+    }
+
+    let item = spec
+        .item
+        .as_ref()
+        .ok_or_else(|| Error::UserInputError("BitwardenSecret spec must set `item` or `inputs`/`templates`".to_string()))?;
+
+    match bw_client.fetch_item(item.clone()) {
+        Ok(fields) => Ok(select_secret_keys(fields, &spec.key_map)),
+        Err(e) => {
+            info!("Resetting bw context");
+            info!("source: {}", e.to_string());
+            bw_client.reset();
+            Err(e)
+        }
+    }
+}
+
+/// Renames fetched field names to secret key names per `key_map`, dropping fields it doesn't name,
+/// or passes all fields through unchanged when `None`.
+fn select_secret_keys(
+    fields: BTreeMap<String, String>,
+    key_map: &Option<BTreeMap<String, String>>,
+) -> BTreeMap<String, String> {
+    match key_map {
+        None => fields,
+        Some(map) => map
+            .iter()
+            .filter_map(|(field, secret_key)| fields.get(field).map(|value| (secret_key.clone(), value.clone())))
+            .collect(),
+    }
+}
+
+/// Fetches every input item into the `"{alias}.{field}"` -> value context
+/// templates are rendered against, bailing out on the first failed fetch.
+fn fetch_template_context(
+    bw_client: &mut dyn SecretProvider,
+    inputs: &[BitwardenInput],
+) -> Result<BTreeMap<String, String>, Error> {
+    let mut context: BTreeMap<String, String> = BTreeMap::new();
+    for input in inputs {
+        let fields = bw_client.fetch_item(input.item.clone())?;
+        for (field, value) in fields {
+            context.insert(format!("{}.{}", input.alias, field), value);
+        }
+    }
+    Ok(context)
+}
+
+/// Renders each `templates` entry against `context` in Handlebars strict
+/// mode, so a reference to a field that was never fetched errors instead of rendering blank.
+fn render_templates(
+    templates: &BTreeMap<String, String>,
+    context: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, Error> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+
+    // Handlebars reads `{{alias.field}}` as a nested path, so the flat
+    // `"alias.field"` keys are regrouped into `{alias: {field: value}}`.
+    let mut nested: BTreeMap<&str, BTreeMap<&str, &str>> = BTreeMap::new();
+    for (key, value) in context {
+        if let Some((alias, field)) = key.split_once('.') {
+            nested.entry(alias).or_default().insert(field, value.as_str());
+        }
+    }
+
+    templates
+        .iter()
+        .map(|(key, template)| {
+            handlebars
+                .render_template(template, &nested)
+                .map(|rendered| (key.clone(), rendered))
+                .map_err(|e| Error::UserInputError(format!("Failed to render template '{}': {}", key, e)))
+        })
+        .collect()
+}
+
+fn on_error(bitwarden_secret: Arc<BitwardenSecret>, error: &Error, context: Arc<ContextData>) -> Action {
     eprintln!("Reconciliation error:\n{:?}.\n{:?}", error, bitwarden_secret);
-    Action::requeue(Duration::from_secs(5)) //TODO exponential backoff
+
+    let namespace = bitwarden_secret.namespace().unwrap_or_else(|| "default".to_string());
+    let key = format!("{}/{}", namespace, bitwarden_secret.name_any());
+
+    let mut failure_counts = context.failure_counts.lock().expect("failure_counts lock poisoned");
+    let attempt = failure_counts.entry(key).or_insert(0);
+    *attempt += 1;
+
+    Action::requeue(exponential_backoff(*attempt))
+}
+
+/// Doubles the backoff per consecutive failure, capped between `MIN_BACKOFF_SECS` and `MAX_BACKOFF_SECS`.
+fn exponential_backoff(attempt: u32) -> Duration {
+    let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+    let secs = MIN_BACKOFF_SECS.saturating_mul(factor).min(MAX_BACKOFF_SECS);
+    Duration::from_secs(secs)
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -262,3 +535,120 @@ pub enum Error {
     #[error("Invalid BitwardenSecret CRD: {0}")]
     UserInputError(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_owner_ref_sets_controlling_owner() {
+        let mut bitwarden_secret = BitwardenSecret::new(
+            "my-secret",
+            BitwardenSecretSpec {
+                type_: "Opaque".to_string(),
+                item: None,
+                key_map: None,
+                inputs: vec![],
+                templates: BTreeMap::new(),
+            },
+        );
+        bitwarden_secret.meta_mut().uid = Some("uid-123".to_string());
+
+        let owner_ref = build_owner_ref(&bitwarden_secret, "my-secret", "default");
+
+        assert_eq!(owner_ref.controller, Some(true), "the `.owns()` watch only re-triggers for the controlling owner");
+    }
+
+    #[test]
+    fn exponential_backoff_doubles_until_capped() {
+        assert_eq!(exponential_backoff(1), Duration::from_secs(5));
+        assert_eq!(exponential_backoff(2), Duration::from_secs(10));
+        assert_eq!(exponential_backoff(3), Duration::from_secs(20));
+        assert_eq!(exponential_backoff(20), Duration::from_secs(MAX_BACKOFF_SECS));
+    }
+
+    #[test]
+    fn select_secret_keys_passes_through_without_key_map() {
+        let mut fields = BTreeMap::new();
+        fields.insert("username".to_string(), "alice".to_string());
+
+        assert_eq!(select_secret_keys(fields.clone(), &None), fields);
+    }
+
+    #[test]
+    fn select_secret_keys_renames_and_drops_unmapped() {
+        let mut fields = BTreeMap::new();
+        fields.insert("username".to_string(), "alice".to_string());
+        fields.insert("password".to_string(), "hunter2".to_string());
+
+        let mut key_map = BTreeMap::new();
+        key_map.insert("username".to_string(), "user".to_string());
+
+        let mapped = select_secret_keys(fields, &Some(key_map));
+
+        let mut expected = BTreeMap::new();
+        expected.insert("user".to_string(), "alice".to_string());
+        assert_eq!(mapped, expected);
+    }
+
+    #[test]
+    fn render_templates_substitutes_input_fields() {
+        let mut context = BTreeMap::new();
+        context.insert("db.username".to_string(), "alice".to_string());
+
+        let mut templates = BTreeMap::new();
+        templates.insert("DB_USER".to_string(), "{{db.username}}".to_string());
+
+        let rendered = render_templates(&templates, &context).unwrap();
+        assert_eq!(rendered.get("DB_USER"), Some(&"alice".to_string()));
+    }
+
+    #[test]
+    fn render_templates_errors_on_missing_input() {
+        let context = BTreeMap::new();
+        let mut templates = BTreeMap::new();
+        templates.insert("DB_USER".to_string(), "{{missing.username}}".to_string());
+
+        assert!(render_templates(&templates, &context).is_err());
+    }
+
+    #[test]
+    fn secret_data_differs_detects_removed_keys() {
+        let mut data = BTreeMap::new();
+        data.insert("username".to_string(), ByteString(b"alice".to_vec()));
+        data.insert("password".to_string(), ByteString(b"hunter2".to_vec()));
+        let secret = Secret { data: Some(data), ..Secret::default() };
+
+        let mut secret_keys = BTreeMap::new();
+        secret_keys.insert("username".to_string(), "alice".to_string());
+
+        assert!(secret_data_differs(&secret, &secret_keys));
+    }
+
+    #[test]
+    fn secret_data_differs_is_false_when_unchanged() {
+        let mut data = BTreeMap::new();
+        data.insert("username".to_string(), ByteString(b"alice".to_vec()));
+        let secret = Secret { data: Some(data), ..Secret::default() };
+
+        let mut secret_keys = BTreeMap::new();
+        secret_keys.insert("username".to_string(), "alice".to_string());
+
+        assert!(!secret_data_differs(&secret, &secret_keys));
+    }
+
+    #[test]
+    fn data_patch_nulls_out_removed_keys() {
+        let mut data = BTreeMap::new();
+        data.insert("username".to_string(), ByteString(b"alice".to_vec()));
+        data.insert("password".to_string(), ByteString(b"hunter2".to_vec()));
+        let existing = Secret { data: Some(data), ..Secret::default() };
+
+        let mut secret_keys = BTreeMap::new();
+        secret_keys.insert("username".to_string(), "alice".to_string());
+
+        let patch = data_patch(&existing, &secret_keys);
+        assert_eq!(patch["data"]["password"], Value::Null);
+        assert_ne!(patch["data"]["username"], Value::Null);
+    }
+}
@@ -0,0 +1,32 @@
+use std::collections::BTreeMap;
+
+use config::Config;
+
+use crate::bw::BitwardenClientWrapper;
+use crate::Error;
+
+/// Resolves a Bitwarden vault item path into its fields. Implemented today
+/// by the CLI wrapper, kept as a trait so other backends can be swapped in.
+pub trait SecretProvider: Send {
+    fn fetch_item(&mut self, path: String) -> Result<BTreeMap<String, String>, Error>;
+    fn reset(&mut self);
+}
+
+impl SecretProvider for BitwardenClientWrapper {
+    fn fetch_item(&mut self, path: String) -> Result<BTreeMap<String, String>, Error> {
+        BitwardenClientWrapper::fetch_item(self, path)
+    }
+
+    fn reset(&mut self) {
+        BitwardenClientWrapper::reset(self)
+    }
+}
+
+/// Selects the `SecretProvider` named by the `provider` config key, defaulting to the CLI wrapper.
+pub fn build_provider(config: Config) -> Result<Box<dyn SecretProvider>, Error> {
+    let provider = config.get_string("provider").unwrap_or_else(|_| "cli".to_string());
+    match provider.as_str() {
+        "cli" => Ok(Box::new(BitwardenClientWrapper::new(config)?)),
+        other => Err(Error::UserInputError(format!("Unknown secret provider: {}", other))),
+    }
+}